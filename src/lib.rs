@@ -1,14 +1,20 @@
 extern crate sodiumoxide;
 extern crate libc;
 extern crate libsodium_sys as ffi;
+extern crate secp256k1;
+#[macro_use]
+extern crate lazy_static;
 
 #[macro_use]
 mod macros;
 pub mod parse_args;
 pub mod generichash;
+pub mod secret_bytes;
+pub mod sign;
 
 use generichash::*;
-use sodiumoxide::crypto::sign::{gen_keypair, SECRETKEYBYTES, PUBLICKEYBYTES, SIGNATUREBYTES};
+use secret_bytes::SecretBytes;
+use sodiumoxide::crypto::sign::{gen_keypair, PUBLICKEYBYTES, SIGNATUREBYTES};
 use sodiumoxide::crypto::pwhash::{OpsLimit, MemLimit, OPSLIMIT_SENSITIVE, MEMLIMIT_SENSITIVE,
                                   SALTBYTES};
 use sodiumoxide::randombytes::*;
@@ -17,6 +23,7 @@ use std::fmt::{Debug, Error, Formatter};
 use std::mem;
 use std::io::Cursor;
 use std::io::Read;
+use std::str::FromStr;
 
 pub const KEYNUMBYTES: usize = 8;
 pub const TWOBYTES: usize = 2;
@@ -26,8 +33,19 @@ pub const COMMENTBYTES: usize = 1024;
 pub const TRUSTEDCOMMENTMAXBYTES: usize = 8192;
 pub const SIGALG: &'static str = "Ed";
 pub const SIGALG_HASHED: &'static str = "ED";
+/// secp256k1 ECDSA, selectable alongside the default Ed25519 algorithm.
+pub const SIGALG_SECP256K1: &'static str = "EC";
+/// Length in bytes of the BLAKE2b-512 digest used by [`sign::prehash`] for
+/// the `SIGALG_HASHED` signing mode.
+pub const PREHASH_BYTES: usize = 64;
 pub const KDFALG: &'static str = "Sc";
 pub const CHKALG: &'static str = "B2";
+/// `chk_alg` marker for a secret key sealed with ChaCha20-Poly1305 instead
+/// of the bare scrypt-keystream XOR, see [`SeckeyStruct::seal_aead`].
+pub const CHKALG_AEAD: &'static str = "CP";
+pub const AEAD_KEYBYTES: usize = 32;
+pub const AEAD_NONCEBYTES: usize = 12;
+pub const AEAD_TAGBYTES: usize = 16;
 pub const COMMENT_PREFIX: &'static str = "untrusted comment: ";
 pub const DEFAULT_COMMENT: &'static str = "signature from rsign secret key";
 pub const SECRETKEY_DEFAULT_COMMENT: &'static str = "rsign encrypted secret key";
@@ -39,11 +57,17 @@ pub const SIG_DEFAULT_SKFILE: &'static str = "rsign.key";
 pub const SIG_SUFFIX: &'static str = ".rsign";
 pub const VERSION_STRING: &'static str = "rsign 0.1";
 
+lazy_static! {
+    /// A single secp256k1 signing/verification context, created once and
+    /// reused across calls rather than re-initialized per sign/verify.
+    static ref SECP256K1_CTX: secp256k1::Secp256k1<secp256k1::All> = secp256k1::Secp256k1::new();
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct KeynumSK {
     pub keynum: Vec<u8>,
-    pub sk: Vec<u8>,
+    /// Locked, zero-on-drop storage for the raw secret key.
+    pub sk: SecretBytes,
     pub chk: Vec<u8>,
 }
 impl KeynumSK {
@@ -62,6 +86,9 @@ pub struct SeckeyStruct {
     pub kdf_opslimit_le: OpsLimit,
     pub kdf_memlimit_le: MemLimit,
     pub keynum_sk: KeynumSK,
+    /// ChaCha20-Poly1305 authentication tag, only populated (and only
+    /// meaningful) when `chk_alg == CHKALG_AEAD`.
+    pub aead_tag: Vec<u8>,
 }
 impl AsRef<[u8]> for SeckeyStruct {
     fn as_ref(&self) -> &[u8] {
@@ -73,6 +100,13 @@ impl SeckeyStruct {
         mem::size_of_val(&self)
     }
     pub fn from(bytes_buf: &[u8]) -> Result<SeckeyStruct, ()> {
+        if bytes_buf.len() < 126 + BYTES {
+            return Err(());
+        }
+        let is_aead = bytes_buf[4..6] == CHKALG_AEAD.as_bytes();
+        if is_aead && bytes_buf.len() < 126 + BYTES + AEAD_TAGBYTES {
+            return Err(());
+        }
         let sk = SeckeyStruct {
             sig_alg: bytes_buf[..2].to_vec(),
             kdf_alg: bytes_buf[2..4].to_vec(),
@@ -82,8 +116,13 @@ impl SeckeyStruct {
             kdf_memlimit_le: MemLimit(load_usize_le(&bytes_buf[46..54])),
             keynum_sk: KeynumSK {
                 keynum: bytes_buf[54..62].to_vec(),
-                sk: bytes_buf[62..126].to_vec(),
-                chk: bytes_buf[126..].to_vec(),
+                sk: SecretBytes::from_slice(&bytes_buf[62..126]),
+                chk: bytes_buf[126..126 + BYTES].to_vec(),
+            },
+            aead_tag: if is_aead {
+                bytes_buf[126 + BYTES..126 + BYTES + AEAD_TAGBYTES].to_vec()
+            } else {
+                Vec::new()
             },
         };
         Ok(sk)
@@ -93,30 +132,79 @@ impl SeckeyStruct {
         let opslim_arr = store_usize_le(op_lim);
         let MemLimit(mem_lim) = self.kdf_memlimit_le;
         let memlim_arr = store_usize_le(mem_lim);
-        let mut opslim_vec = Vec::new();
-        let mut memlim_vec = Vec::new();
-        opslim_vec.extend_from_slice(&opslim_arr[..]);
-        memlim_vec.extend_from_slice(&memlim_arr[..]);
-
-        let mut iters = Vec::new();
-        iters.push(self.sig_alg.iter());
-        iters.push(self.kdf_alg.iter());
-        iters.push(self.chk_alg.iter());
-        iters.push(self.kdf_salt.iter());
-        iters.push(opslim_vec.iter());
-        iters.push(memlim_vec.iter());
-        iters.push(self.keynum_sk.keynum.iter());
-        iters.push(self.keynum_sk.sk.iter());
-        iters.push(self.keynum_sk.chk.iter());
-        let v: Vec<u8> = iters
-            .iter()
-            .flat_map(|b| {
-                          let b = b.clone();
-                          b.into_iter().cloned()
-                      })
-            .collect();
+        let mut v = Vec::new();
+        v.extend_from_slice(&self.sig_alg);
+        v.extend_from_slice(&self.kdf_alg);
+        v.extend_from_slice(&self.chk_alg);
+        v.extend_from_slice(&self.kdf_salt);
+        v.extend_from_slice(&opslim_arr);
+        v.extend_from_slice(&memlim_arr);
+        v.extend_from_slice(&self.keynum_sk.keynum);
+        v.extend_from_slice(&self.keynum_sk.sk.borrow());
+        v.extend_from_slice(&self.keynum_sk.chk);
+        if self.chk_alg == CHKALG_AEAD.as_bytes() {
+            v.extend_from_slice(&self.aead_tag);
+        }
         v
     }
+    /// Seals `keynum || sk || chk` in place with ChaCha20-Poly1305 (IETF,
+    /// 12-byte nonce), storing the resulting authentication tag in
+    /// `self.aead_tag` and setting `chk_alg` to `CHKALG_AEAD`. An
+    /// alternative to [`SeckeyStruct::xor_keynum`] that detects a wrong
+    /// password or a tampered file via tag verification, before any
+    /// plaintext is ever exposed.
+    pub fn seal_aead(&mut self, key: &[u8; AEAD_KEYBYTES], nonce: &[u8; AEAD_NONCEBYTES]) {
+        use sodiumoxide::crypto::aead::chacha20poly1305_ietf::{seal_detached, Key, Nonce};
+
+        let mut payload = Vec::with_capacity(self.keynum_sk.len());
+        payload.extend_from_slice(&self.keynum_sk.keynum);
+        payload.extend_from_slice(&self.keynum_sk.sk.borrow());
+        payload.extend_from_slice(&self.keynum_sk.chk);
+
+        let tag = seal_detached(&mut payload, Some(self.sig_alg.as_ref()), &Nonce(*nonce), &Key(*key));
+
+        let keynum_len = self.keynum_sk.keynum.len();
+        let sk_len = self.keynum_sk.sk.len();
+        self.keynum_sk.keynum.copy_from_slice(&payload[..keynum_len]);
+        self.keynum_sk.sk.borrow_mut().copy_from_slice(&payload[keynum_len..keynum_len + sk_len]);
+        self.keynum_sk.chk.copy_from_slice(&payload[keynum_len + sk_len..]);
+        sodiumoxide::utils::memzero(&mut payload);
+        self.aead_tag = tag.as_ref().to_vec();
+        self.chk_alg = CHKALG_AEAD.bytes().collect();
+    }
+    /// Opens a key sealed with [`SeckeyStruct::seal_aead`] in place,
+    /// verifying the Poly1305 tag before any plaintext is exposed. A wrong
+    /// password or a tampered file is rejected here, before
+    /// [`SeckeyStruct::checksum`]'s post-hoc BLAKE2b check ever runs.
+    pub fn open_aead(&mut self, key: &[u8; AEAD_KEYBYTES], nonce: &[u8; AEAD_NONCEBYTES]) -> Result<(), std::io::Error> {
+        use sodiumoxide::crypto::aead::chacha20poly1305_ietf::{open_detached, Key, Nonce, Tag};
+
+        let mut tag_arr = [0u8; AEAD_TAGBYTES];
+        tag_arr.copy_from_slice(&self.aead_tag);
+        let tag = Tag(tag_arr);
+
+        let mut ciphertext = Vec::with_capacity(self.keynum_sk.len());
+        ciphertext.extend_from_slice(&self.keynum_sk.keynum);
+        ciphertext.extend_from_slice(&self.keynum_sk.sk.borrow());
+        ciphertext.extend_from_slice(&self.keynum_sk.chk);
+
+        let open_result = open_detached(&mut ciphertext, Some(self.sig_alg.as_ref()), &tag, &Nonce(*nonce), &Key(*key));
+        if open_result.is_err() {
+            sodiumoxide::utils::memzero(&mut ciphertext);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "AEAD tag verification failed: wrong password or tampered secret-key file",
+            ));
+        }
+
+        let keynum_len = self.keynum_sk.keynum.len();
+        let sk_len = self.keynum_sk.sk.len();
+        self.keynum_sk.keynum.copy_from_slice(&ciphertext[..keynum_len]);
+        self.keynum_sk.sk.borrow_mut().copy_from_slice(&ciphertext[keynum_len..keynum_len + sk_len]);
+        self.keynum_sk.chk.copy_from_slice(&ciphertext[keynum_len + sk_len..]);
+        sodiumoxide::utils::memzero(&mut ciphertext);
+        Ok(())
+    }
     pub fn checksum(&mut self) {
         let state_sz = unsafe { ffi::crypto_generichash_statebytes() };
         let mut state: Vec<u8> = vec![0;state_sz];
@@ -124,10 +212,14 @@ impl SeckeyStruct {
         generichash::init(ptr_state).unwrap();
         generichash::update(ptr_state, self.sig_alg.as_ref()).unwrap();
         generichash::update(ptr_state, self.keynum_sk.keynum.as_ref()).unwrap();
-        generichash::update(ptr_state, self.keynum_sk.sk.as_ref()).unwrap();
+        generichash::update(ptr_state, self.keynum_sk.sk.borrow().as_ref()).unwrap();
         let h = generichash::finalize(ptr_state).unwrap();
         self.keynum_sk.chk = h.as_ref().to_vec();
     }
+    /// XORs the stored `keynum || sk || chk` with a scrypt-derived
+    /// keystream, decrypting (or encrypting) the secret key in place. `sk`
+    /// is only ever touched through its [`SecretBytes`] guard, so it's
+    /// never copied out into an unlocked, unzeroed buffer.
     pub fn xor_keynum(&mut self, mut stream: Vec<u8>) {
 
         let b8 = self.keynum_sk
@@ -137,12 +229,13 @@ impl SeckeyStruct {
             .map(|(byte, stream)| *byte = *byte ^ *stream)
             .count();
 
-        let b64 = self.keynum_sk
-            .sk
+        let mut sk = self.keynum_sk.sk.borrow_mut();
+        let b64 = sk
             .iter_mut()
             .zip(stream[b8..].iter())
             .map(|(byte, stream)| *byte = *byte ^ *stream)
             .count();
+        drop(sk);
 
         let _b32 = self.keynum_sk
             .chk
@@ -181,18 +274,20 @@ pub struct PubkeyStruct {
 #[derive(Debug, Clone)]
 pub struct KeynumPK {
     pub keynum: [u8;KEYNUMBYTES],
-    pub pk: [u8;PUBLICKEYBYTES],
+    // Variable length so both 32-byte Ed25519 keys and 33/65-byte
+    // compressed/uncompressed secp256k1 keys fit the same struct.
+    pub pk: Vec<u8>,
 }
 impl PubkeyStruct {
-    
+
     pub fn from(buf: &[u8]) -> Result<PubkeyStruct, std::io::Error> {
         let mut buf = Cursor::new(buf);
         let mut sig_alg = [0u8;2];
         let mut keynum = [0u8;KEYNUMBYTES];
-        let mut pk = [0u8;PUBLICKEYBYTES];
+        let mut pk = Vec::new();
         buf.read(&mut sig_alg)?;
         buf.read(&mut keynum)?;
-        buf.read(&mut pk)?;
+        buf.read_to_end(&mut pk)?;
         Ok(PubkeyStruct {
             sig_alg: sig_alg,
             keynum_pk: KeynumPK {
@@ -223,6 +318,11 @@ pub struct SigStruct {
     pub sig_alg: Vec<u8>,
     pub keynum: Vec<u8>,
     pub sig: Vec<u8>,
+    /// Ed25519 signature over `sig || trusted_comment`, binding the trusted
+    /// comment to this signature so it can't be rewritten afterwards.
+    pub global_sig: Vec<u8>,
+    pub trusted_comment: String,
+    pub untrusted_comment: String,
 }
 impl SigStruct {
     pub fn bytes(&self) -> Vec<u8> {
@@ -240,12 +340,82 @@ impl SigStruct {
         v
     }
     pub fn from(bytes_buf: &[u8]) -> Result<SigStruct, ()> {
+        // `sig` is read to the end of the buffer rather than a fixed 64
+        // bytes so both compact Ed25519/secp256k1 signatures and
+        // variable-length secp256k1 DER signatures round-trip.
         Ok(SigStruct {
             sig_alg: bytes_buf[..2].to_vec(),
             keynum: bytes_buf[2..10].to_vec(),
-            sig: bytes_buf[10..74].to_vec(),   
+            sig: bytes_buf[10..].to_vec(),
+            ..SigStruct::default()
         })
     }
+
+    /// Verifies this signature over `message` against `pk`, dispatching on
+    /// `sig_alg`.
+    pub fn verify(&self, pk: &PubkeyStruct, message: &[u8]) -> bool {
+        let sig_alg = String::from_utf8_lossy(&self.sig_alg);
+        verify(&sig_alg, &pk.keynum_pk.pk, message, &self.sig)
+    }
+
+    /// Computes the global signature binding `self.sig` to
+    /// `trusted_comment` and stores both, per minisign's global-signature
+    /// scheme: `global_sig = Ed25519(sk, sig || trusted_comment)`.
+    ///
+    /// The minisign global-signature scheme is ed25519-only; this returns
+    /// an error instead of panicking when `sk` is a secp256k1 key.
+    pub fn sign_trusted_comment(&mut self, sk: &SeckeyStruct, trusted_comment: &str) -> Result<(), std::io::Error> {
+        use sodiumoxide::crypto::sign::ed25519;
+        if sk.sig_alg != SIGALG.as_bytes() && sk.sig_alg != SIGALG_HASHED.as_bytes() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "trusted-comment signing requires an ed25519 secret key",
+            ));
+        }
+        let message = self.global_sig_message(trusted_comment);
+        let sk = ed25519::SecretKey::from_slice(&sk.keynum_sk.sk.borrow())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid ed25519 secret key"))?;
+        self.global_sig = ed25519::sign_detached(&message, &sk).0.to_vec();
+        self.trusted_comment = trusted_comment.to_string();
+        Ok(())
+    }
+
+    /// Verifies that `self.global_sig` is a valid signature over
+    /// `self.sig || self.trusted_comment` under `pk`. Callers must check
+    /// this before trusting `self.trusted_comment`, since the comment line
+    /// of a `.minisig` file can otherwise be rewritten freely.
+    ///
+    /// Like `sign_trusted_comment`, this is ed25519-only and errors out
+    /// (rather than panicking) for a secp256k1 `pk`.
+    pub fn verify_global_sig(&self, pk: &PubkeyStruct) -> Result<(), std::io::Error> {
+        use sodiumoxide::crypto::sign::ed25519;
+        if &pk.sig_alg[..] != SIGALG.as_bytes() && &pk.sig_alg[..] != SIGALG_HASHED.as_bytes() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "trusted-comment verification requires an ed25519 public key",
+            ));
+        }
+        let message = self.global_sig_message(&self.trusted_comment);
+        let pk = ed25519::PublicKey::from_slice(&pk.keynum_pk.pk)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid ed25519 public key"))?;
+        let sig = ed25519::Signature::from_slice(&self.global_sig)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid global signature length"))?;
+        if ed25519::verify_detached(&sig, &message, &pk) {
+            Ok(())
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "global signature verification failed: trusted comment may have been tampered with",
+            ))
+        }
+    }
+
+    fn global_sig_message(&self, trusted_comment: &str) -> Vec<u8> {
+        let mut message = Vec::with_capacity(self.sig.len() + trusted_comment.len());
+        message.extend_from_slice(&self.sig);
+        message.extend_from_slice(trusted_comment.as_bytes());
+        message
+    }
 }
 
 impl Default for SigStruct {
@@ -254,19 +424,34 @@ impl Default for SigStruct {
             sig_alg: vec![0u8;2],
             keynum: vec![0u8;8],
             sig: vec![0u8;64],
+            global_sig: vec![0u8;64],
+            trusted_comment: String::new(),
+            untrusted_comment: String::new(),
         }
     }
 }
 
 pub fn gen_keystruct() -> (PubkeyStruct, SeckeyStruct) {
+    gen_keystruct_for(SIGALG)
+}
+
+/// Generates a keypair for the given `sig_alg` (`SIGALG` or
+/// `SIGALG_SECP256K1`).
+pub fn gen_keystruct_for(sig_alg: &str) -> (PubkeyStruct, SeckeyStruct) {
+    match sig_alg {
+        SIGALG_SECP256K1 => gen_keystruct_secp256k1(),
+        _ => gen_keystruct_ed25519(),
+    }
+}
+
+fn gen_keystruct_ed25519() -> (PubkeyStruct, SeckeyStruct) {
     let (pk, sk) = gen_keypair();
-    let mut pk_vec = [0u8;PUBLICKEYBYTES];
-    let mut sk_vec = Vec::with_capacity(SECRETKEYBYTES);
+    let mut pk_vec = Vec::with_capacity(PUBLICKEYBYTES);
+    let sk_bytes = SecretBytes::from_slice(&sk[..]);
     let keynum_vec = randombytes(KEYNUMBYTES);
     let mut keynum = [0u8;KEYNUMBYTES];
     keynum.copy_from_slice(keynum_vec.as_slice());
-    pk_vec.copy_from_slice(&pk[..]);
-    sk_vec.extend_from_slice(&sk[..]);
+    pk_vec.extend_from_slice(&pk[..]);
     let mut sig_alg = [0u8;2];
     sig_alg.copy_from_slice(&SIGALG.as_bytes()[..]);
     let p_struct = PubkeyStruct {
@@ -285,13 +470,331 @@ pub fn gen_keystruct() -> (PubkeyStruct, SeckeyStruct) {
         kdf_memlimit_le: MEMLIMIT_SENSITIVE,
         keynum_sk: KeynumSK {
             keynum: keynum_vec,
-            sk: sk_vec,
+            sk: sk_bytes,
+            chk: Vec::with_capacity(BYTES),
+        },
+        aead_tag: Vec::new(),
+    };
+    (p_struct, s_struct)
+}
+
+fn gen_keystruct_secp256k1() -> (PubkeyStruct, SeckeyStruct) {
+    gen_keystruct_secp256k1_with_format(Secp256k1PubkeyFormat::Compressed)
+}
+
+/// Public key encoding for a freshly generated secp256k1 keypair: 33-byte
+/// compressed (the default) or 65-byte uncompressed.
+pub enum Secp256k1PubkeyFormat {
+    Compressed,
+    Uncompressed,
+}
+
+/// Generates a secp256k1 keypair, encoding the public key in either the
+/// 33-byte compressed or 65-byte uncompressed form.
+pub fn gen_keystruct_secp256k1_with_format(format: Secp256k1PubkeyFormat) -> (PubkeyStruct, SeckeyStruct) {
+    let ctx = &*SECP256K1_CTX;
+    let sk = loop {
+        let mut candidate = randombytes(32);
+        let parsed = secp256k1::SecretKey::from_slice(&candidate);
+        sodiumoxide::utils::memzero(&mut candidate);
+        if let Ok(sk) = parsed {
+            break sk;
+        }
+    };
+    let pk = secp256k1::PublicKey::from_secret_key(ctx, &sk);
+    let pk_vec = match format {
+        Secp256k1PubkeyFormat::Compressed => pk.serialize().to_vec(),
+        Secp256k1PubkeyFormat::Uncompressed => pk.serialize_uncompressed().to_vec(),
+    };
+    let sk_bytes = SecretBytes::from_slice(&sk[..]);
+    let keynum_vec = randombytes(KEYNUMBYTES);
+    let mut keynum = [0u8;KEYNUMBYTES];
+    keynum.copy_from_slice(keynum_vec.as_slice());
+    let mut sig_alg = [0u8;2];
+    sig_alg.copy_from_slice(&SIGALG_SECP256K1.as_bytes()[..]);
+    let p_struct = PubkeyStruct {
+        sig_alg: sig_alg,
+        keynum_pk: KeynumPK {
+            keynum: keynum,
+            pk: pk_vec,
+        },
+    };
+    let s_struct = SeckeyStruct {
+        sig_alg: SIGALG_SECP256K1.bytes().collect(),
+        kdf_alg: KDFALG.bytes().collect(),
+        chk_alg: CHKALG.bytes().collect(),
+        kdf_salt: randombytes(SALTBYTES),
+        kdf_opslimit_le: OPSLIMIT_SENSITIVE,
+        kdf_memlimit_le: MEMLIMIT_SENSITIVE,
+        keynum_sk: KeynumSK {
+            keynum: keynum_vec,
+            sk: sk_bytes,
             chk: Vec::with_capacity(BYTES),
         },
+        aead_tag: Vec::new(),
     };
     (p_struct, s_struct)
 }
 
+/// Derives a 32-byte AEAD key and 12-byte nonce from `password` via scrypt,
+/// using the same salt/opslimit/memlimit the secret-key container already
+/// carries for the XOR keystream.
+pub fn derive_aead_key_nonce(
+    password: &[u8],
+    salt: &[u8],
+    ops_limit: OpsLimit,
+    mem_limit: MemLimit,
+) -> ([u8; AEAD_KEYBYTES], [u8; AEAD_NONCEBYTES]) {
+    use sodiumoxide::crypto::pwhash::scryptsalsa208sha256::{derive_key, Salt};
+
+    let mut salt_arr = [0u8; SALTBYTES];
+    salt_arr.copy_from_slice(salt);
+    let salt = Salt(salt_arr);
+
+    let mut out = [0u8; AEAD_KEYBYTES + AEAD_NONCEBYTES];
+    derive_key(&mut out, password, &salt, ops_limit, mem_limit).expect("scrypt key derivation failed");
+
+    let mut key = [0u8; AEAD_KEYBYTES];
+    let mut nonce = [0u8; AEAD_NONCEBYTES];
+    key.copy_from_slice(&out[..AEAD_KEYBYTES]);
+    nonce.copy_from_slice(&out[AEAD_KEYBYTES..]);
+    (key, nonce)
+}
+
+/// Signs `message` with `sk_bytes`, dispatching on `sig_alg`
+/// (`SIGALG` or `SIGALG_SECP256K1`).
+///
+/// secp256k1 signs a 32-byte digest rather than arbitrary-length content,
+/// so `message` must be exactly 32 bytes in that mode; this is reported as
+/// an `Err` rather than a panic so callers that dispatch across algorithms
+/// generically don't need to special-case secp256k1.
+pub fn sign(sig_alg: &str, sk_bytes: &[u8], message: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    if sig_alg == SIGALG_SECP256K1 {
+        let ctx = &*SECP256K1_CTX;
+        let msg = secp256k1::Message::from_slice(message).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("secp256k1 signs a 32-byte digest, not raw content: {}", e),
+            )
+        })?;
+        let sk = secp256k1::SecretKey::from_slice(sk_bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid secp256k1 secret key: {}", e)))?;
+        Ok(ctx.sign_ecdsa(&msg, &sk).serialize_compact().to_vec())
+    } else {
+        use sodiumoxide::crypto::sign::ed25519;
+        let sk = ed25519::SecretKey::from_slice(sk_bytes)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid ed25519 secret key"))?;
+        Ok(ed25519::sign_detached(message, &sk).0.to_vec())
+    }
+}
+
+/// Verifies `sig_bytes` over `message` against `pk_bytes`, dispatching on
+/// `sig_alg`. Accepts either compact or DER-encoded secp256k1 signatures.
+pub fn verify(sig_alg: &str, pk_bytes: &[u8], message: &[u8], sig_bytes: &[u8]) -> bool {
+    if sig_alg == SIGALG_SECP256K1 {
+        let ctx = &*SECP256K1_CTX;
+        let msg = match secp256k1::Message::from_slice(message) {
+            Ok(m) => m,
+            Err(_) => return false,
+        };
+        let pk = match secp256k1::PublicKey::from_slice(pk_bytes) {
+            Ok(k) => k,
+            Err(_) => return false,
+        };
+        let sig = secp256k1::ecdsa::Signature::from_compact(sig_bytes)
+            .or_else(|_| secp256k1::ecdsa::Signature::from_der(sig_bytes));
+        match sig {
+            Ok(sig) => ctx.verify_ecdsa(&msg, &sig, &pk).is_ok(),
+            Err(_) => false,
+        }
+    } else {
+        use sodiumoxide::crypto::sign::ed25519;
+        let pk = match ed25519::PublicKey::from_slice(pk_bytes) {
+            Some(k) => k,
+            None => return false,
+        };
+        let sig = match ed25519::Signature::from_slice(sig_bytes) {
+            Some(s) => s,
+            None => return false,
+        };
+        ed25519::verify_detached(&sig, message, &pk)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, std::io::Error> {
+    if s.len() % 2 != 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "hex string must have an even length"));
+    }
+    if !s.is_ascii() || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "hex string contains non-hex characters"));
+    }
+    let bytes = s.as_bytes();
+    let out = bytes
+        .chunks(2)
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16).unwrap();
+            let lo = (pair[1] as char).to_digit(16).unwrap();
+            ((hi << 4) | lo) as u8
+        })
+        .collect();
+    Ok(out)
+}
+
+/// Validates that `bytes` has the exact length and `sig_alg` marker of a
+/// supported `PubkeyStruct` encoding (32-byte Ed25519, or 33/65-byte
+/// compressed/uncompressed secp256k1) before `PubkeyStruct::from` is
+/// allowed to parse it.
+fn validate_pubkey_bytes(bytes: &[u8]) -> Result<(), std::io::Error> {
+    if bytes.len() < TWOBYTES + KEYNUMBYTES {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "public key too short"));
+    }
+    let pk_len = bytes.len() - TWOBYTES - KEYNUMBYTES;
+    let valid = match &bytes[..TWOBYTES] {
+        b if b == SIGALG.as_bytes() => pk_len == PUBLICKEYBYTES,
+        b if b == SIGALG_SECP256K1.as_bytes() => pk_len == 33 || pk_len == 65,
+        _ => false,
+    };
+    if !valid {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "unsupported sig_alg or public key length"));
+    }
+    Ok(())
+}
+
+impl PubkeyStruct {
+    /// Hex-encodes this key's `bytes()` representation.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.bytes())
+    }
+
+    /// Parses a lowercase hex-encoded public key, validating its length and
+    /// `sig_alg` the same way [`FromStr::from_str`] does.
+    pub fn from_hex(s: &str) -> Result<PubkeyStruct, std::io::Error> {
+        let bytes = from_hex(s)?;
+        validate_pubkey_bytes(&bytes)?;
+        PubkeyStruct::from(&bytes)
+    }
+}
+
+impl ToString for PubkeyStruct {
+    fn to_string(&self) -> String {
+        base64::encode(self.bytes())
+    }
+}
+
+impl FromStr for PubkeyStruct {
+    type Err = std::io::Error;
+
+    /// Decodes a base64-encoded public key, validating that its length and
+    /// `sig_alg` match a supported encoding (32-byte Ed25519, or 33/65-byte
+    /// compressed/uncompressed secp256k1).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = base64::decode(s.trim())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid base64 public key: {}", e)))?;
+        validate_pubkey_bytes(&bytes)?;
+        PubkeyStruct::from(&bytes)
+    }
+}
+
+/// Length of a `SeckeyStruct::bytes()` XOR-format key (no trailing AEAD tag).
+const SECKEY_XOR_LEN: usize = 126 + BYTES;
+/// Length of a `SeckeyStruct::bytes()` AEAD-format key (with trailing tag).
+const SECKEY_AEAD_LEN: usize = SECKEY_XOR_LEN + AEAD_TAGBYTES;
+
+impl SeckeyStruct {
+    /// Hex-encodes this key's `bytes()` representation.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.bytes())
+    }
+
+    /// Parses a lowercase hex-encoded secret key, either XOR or AEAD format.
+    pub fn from_hex(s: &str) -> Result<SeckeyStruct, ()> {
+        let bytes = from_hex(s).map_err(|_| ())?;
+        if bytes.len() != SECKEY_XOR_LEN && bytes.len() != SECKEY_AEAD_LEN {
+            return Err(());
+        }
+        SeckeyStruct::from(&bytes)
+    }
+}
+
+impl ToString for SeckeyStruct {
+    fn to_string(&self) -> String {
+        base64::encode(self.bytes())
+    }
+}
+
+impl FromStr for SeckeyStruct {
+    type Err = std::io::Error;
+
+    /// Decodes a base64-encoded secret key, validating its length against
+    /// the XOR and AEAD container sizes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = base64::decode(s.trim())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid base64 secret key: {}", e)))?;
+        if bytes.len() != SECKEY_XOR_LEN && bytes.len() != SECKEY_AEAD_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unexpected secret key length {}", bytes.len()),
+            ));
+        }
+        SeckeyStruct::from(&bytes).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "malformed secret key"))
+    }
+}
+
+/// Minimum length of a `SigStruct::bytes()` encoding (`sig_alg || keynum`);
+/// `sig` itself is variable-length (64-byte compact, or DER-encoded for
+/// secp256k1).
+const SIG_MIN_LEN: usize = TWOBYTES + KEYNUMBYTES;
+
+impl SigStruct {
+    /// Hex-encodes this signature's `bytes()` representation (the
+    /// `sig_alg || keynum || sig` triple; the global signature and
+    /// comments are not part of this encoding).
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.bytes())
+    }
+
+    /// Parses a lowercase hex-encoded signature.
+    pub fn from_hex(s: &str) -> Result<SigStruct, ()> {
+        let bytes = from_hex(s).map_err(|_| ())?;
+        if bytes.len() < SIG_MIN_LEN {
+            return Err(());
+        }
+        SigStruct::from(&bytes)
+    }
+}
+
+impl ToString for SigStruct {
+    fn to_string(&self) -> String {
+        base64::encode(self.bytes())
+    }
+}
+
+impl FromStr for SigStruct {
+    type Err = std::io::Error;
+
+    /// Decodes a base64-encoded signature, validating a minimum length for
+    /// `sig_alg || keynum`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = base64::decode(s.trim())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid base64 signature: {}", e)))?;
+        if bytes.len() < SIG_MIN_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("signature too short: expected at least {} bytes, got {}", SIG_MIN_LEN, bytes.len()),
+            ));
+        }
+        SigStruct::from(&bytes).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "malformed signature"))
+    }
+}
+
 pub fn store_usize_le(x: usize) -> [u8; 8] {
     let b1: u8 = (x & 0xff) as u8;
     let b2: u8 = ((x >> 8) & 0xff) as u8;
@@ -309,3 +812,158 @@ pub fn load_usize_le(x: &[u8]) -> usize {
     (x[4] as usize) << 32 | (x[5] as usize) << 40 |
     (x[6] as usize) << 48 | (x[7] as usize) << 56
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secp256k1_sign_and_verify_round_trip() {
+        let (pk, sk) = gen_keystruct_for(SIGALG_SECP256K1);
+        let digest = sodiumoxide::randombytes::randombytes(32);
+        let sig = sign(SIGALG_SECP256K1, &sk.keynum_sk.sk.borrow(), &digest).unwrap();
+        assert!(verify(SIGALG_SECP256K1, &pk.keynum_pk.pk, &digest, &sig));
+
+        let mut tampered_digest = digest.clone();
+        tampered_digest[0] ^= 0xff;
+        assert!(!verify(SIGALG_SECP256K1, &pk.keynum_pk.pk, &tampered_digest, &sig));
+    }
+
+    #[test]
+    fn secp256k1_supports_compressed_and_uncompressed_public_keys() {
+        let (compressed_pk, _) = gen_keystruct_secp256k1_with_format(Secp256k1PubkeyFormat::Compressed);
+        let (uncompressed_pk, _) = gen_keystruct_secp256k1_with_format(Secp256k1PubkeyFormat::Uncompressed);
+        assert_eq!(compressed_pk.keynum_pk.pk.len(), 33);
+        assert_eq!(uncompressed_pk.keynum_pk.pk.len(), 65);
+    }
+
+    #[test]
+    fn secp256k1_sign_rejects_non_digest_message_instead_of_panicking() {
+        let (_, sk) = gen_keystruct_for(SIGALG_SECP256K1);
+        let not_a_digest = b"too short";
+        assert!(sign(SIGALG_SECP256K1, &sk.keynum_sk.sk.borrow(), not_a_digest).is_err());
+    }
+
+    #[test]
+    fn aead_seal_and_open_round_trips() {
+        let (_, mut sk) = gen_keystruct();
+        sk.checksum();
+        let original_sk = sk.keynum_sk.sk.borrow().to_vec();
+        let (key, nonce) =
+            derive_aead_key_nonce(b"correct horse battery staple", &sk.kdf_salt, sk.kdf_opslimit_le, sk.kdf_memlimit_le);
+
+        sk.seal_aead(&key, &nonce);
+        assert_eq!(sk.chk_alg, CHKALG_AEAD.as_bytes());
+        assert_ne!(sk.keynum_sk.sk.borrow().as_ref(), original_sk.as_slice());
+
+        sk.open_aead(&key, &nonce).unwrap();
+        assert_eq!(sk.keynum_sk.sk.borrow().as_ref(), original_sk.as_slice());
+    }
+
+    #[test]
+    fn aead_open_rejects_wrong_password() {
+        let (_, mut sk) = gen_keystruct();
+        sk.checksum();
+        let (key, nonce) =
+            derive_aead_key_nonce(b"correct horse battery staple", &sk.kdf_salt, sk.kdf_opslimit_le, sk.kdf_memlimit_le);
+        sk.seal_aead(&key, &nonce);
+
+        let (wrong_key, _) = derive_aead_key_nonce(b"wrong password", &sk.kdf_salt, sk.kdf_opslimit_le, sk.kdf_memlimit_le);
+        assert!(sk.open_aead(&wrong_key, &nonce).is_err());
+    }
+
+    #[test]
+    fn seckey_from_rejects_truncated_aead_buffer_instead_of_panicking() {
+        let (_, mut sk) = gen_keystruct();
+        sk.checksum();
+        let mut bytes = sk.bytes();
+        // XOR-length buffer (no trailing AEAD tag) whose chk_alg bytes an
+        // attacker set to CHKALG_AEAD: SeckeyStruct::from must reject this
+        // rather than slicing past the end of the buffer.
+        bytes[4..6].copy_from_slice(CHKALG_AEAD.as_bytes());
+        assert!(SeckeyStruct::from(&bytes).is_err());
+    }
+
+    #[test]
+    fn gen_keystruct_round_trips_through_bytes() {
+        let (pk, mut sk) = gen_keystruct();
+        sk.checksum();
+        let pk_bytes = pk.bytes();
+        let sk_bytes = sk.bytes();
+        assert_eq!(pk_bytes.len(), 2 + KEYNUMBYTES + PUBLICKEYBYTES);
+        assert_eq!(PubkeyStruct::from(&pk_bytes).unwrap().keynum_pk.pk, pk.keynum_pk.pk);
+        assert_eq!(SeckeyStruct::from(&sk_bytes).unwrap().keynum_sk.sk.borrow().as_ref(), sk.keynum_sk.sk.borrow().as_ref());
+    }
+
+    #[test]
+    fn xor_keynum_is_its_own_inverse() {
+        let (_, mut sk) = gen_keystruct();
+        sk.checksum();
+        let original_sk = sk.keynum_sk.sk.borrow().to_vec();
+        let stream = sodiumoxide::randombytes::randombytes(sk.keynum_sk.len());
+
+        sk.xor_keynum(stream.clone());
+        assert_ne!(sk.keynum_sk.sk.borrow().as_ref(), original_sk.as_slice());
+
+        sk.xor_keynum(stream);
+        assert_eq!(sk.keynum_sk.sk.borrow().as_ref(), original_sk.as_slice());
+    }
+
+    #[test]
+    fn pubkey_base64_and_hex_round_trip() {
+        let (pk, _) = gen_keystruct();
+        let from_base64 = PubkeyStruct::from_str(&pk.to_string()).unwrap();
+        assert_eq!(from_base64.keynum_pk.pk, pk.keynum_pk.pk);
+        let from_hex = PubkeyStruct::from_hex(&pk.to_hex()).unwrap();
+        assert_eq!(from_hex.keynum_pk.pk, pk.keynum_pk.pk);
+    }
+
+    #[test]
+    fn pubkey_from_str_rejects_garbage() {
+        assert!(PubkeyStruct::from_str("not valid base64 at all!!").is_err());
+        assert!(PubkeyStruct::from_str("").is_err());
+    }
+
+    #[test]
+    fn pubkey_from_hex_rejects_short_input_instead_of_returning_a_bogus_key() {
+        assert!(PubkeyStruct::from_hex("").is_err());
+        assert!(PubkeyStruct::from_hex("deadbeef").is_err());
+    }
+
+    #[test]
+    fn seckey_base64_and_hex_round_trip() {
+        let (_, mut sk) = gen_keystruct();
+        sk.checksum();
+        let from_base64 = SeckeyStruct::from_str(&sk.to_string()).unwrap();
+        assert_eq!(from_base64.keynum_sk.sk.borrow().as_ref(), sk.keynum_sk.sk.borrow().as_ref());
+        let from_hex = SeckeyStruct::from_hex(&sk.to_hex()).unwrap();
+        assert_eq!(from_hex.keynum_sk.sk.borrow().as_ref(), sk.keynum_sk.sk.borrow().as_ref());
+    }
+
+    #[test]
+    fn seckey_from_str_rejects_wrong_length() {
+        let short = base64::encode(&[0u8; 10]);
+        assert!(SeckeyStruct::from_str(&short).is_err());
+    }
+
+    #[test]
+    fn sig_base64_and_hex_round_trip() {
+        let (_, sk) = gen_keystruct();
+        let sig_bytes = sign(SIGALG, &sk.keynum_sk.sk.borrow(), b"hello").unwrap();
+        let sig = SigStruct {
+            sig_alg: SIGALG.bytes().collect(),
+            keynum: sk.keynum_sk.keynum.clone(),
+            sig: sig_bytes,
+            ..SigStruct::default()
+        };
+        let from_base64 = SigStruct::from_str(&sig.to_string()).unwrap();
+        assert_eq!(from_base64.sig, sig.sig);
+        let from_hex = SigStruct::from_hex(&sig.to_hex()).unwrap();
+        assert_eq!(from_hex.sig, sig.sig);
+    }
+
+    #[test]
+    fn sig_from_hex_rejects_non_hex_characters() {
+        assert!(SigStruct::from_hex("not hexadecimal digits here").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+
+/// A heap allocation for secret key material that is locked out of swap,
+/// excluded from core dumps, and wiped on drop.
+///
+/// The bytes are only reachable through [`SecretBytes::borrow`] /
+/// [`SecretBytes::borrow_mut`], which hand back a guard rather than a raw
+/// slice, so nothing can stash a long-lived reference past the guard's
+/// scope.
+pub struct SecretBytes {
+    buf: Box<[u8]>,
+}
+
+impl SecretBytes {
+    /// Allocates `len` zeroed, locked bytes.
+    pub fn new(len: usize) -> SecretBytes {
+        let mut buf: Box<[u8]> = vec![0u8; len].into_boxed_slice();
+        lock(&mut buf);
+        SecretBytes { buf }
+    }
+
+    /// Allocates locked storage and copies `bytes` into it.
+    pub fn from_slice(bytes: &[u8]) -> SecretBytes {
+        let mut secret = SecretBytes::new(bytes.len());
+        secret.borrow_mut().copy_from_slice(bytes);
+        secret
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns a scoped read guard over the protected bytes.
+    pub fn borrow(&self) -> SecretBytesGuard {
+        SecretBytesGuard { buf: &self.buf }
+    }
+
+    /// Returns a scoped read/write guard over the protected bytes.
+    pub fn borrow_mut(&mut self) -> SecretBytesGuardMut {
+        SecretBytesGuardMut { buf: &mut self.buf }
+    }
+}
+
+impl Clone for SecretBytes {
+    fn clone(&self) -> SecretBytes {
+        SecretBytes::from_slice(&self.buf)
+    }
+}
+
+impl Default for SecretBytes {
+    fn default() -> SecretBytes {
+        SecretBytes::new(0)
+    }
+}
+
+// Never derive or implement this against the actual bytes: the secret must
+// not be reachable through `{:?}`.
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes({} bytes, redacted)", self.buf.len())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        zero(&mut self.buf);
+        unlock(&mut self.buf);
+    }
+}
+
+/// Scoped read-only access to the bytes behind a [`SecretBytes`].
+pub struct SecretBytesGuard<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Deref for SecretBytesGuard<'a> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.buf
+    }
+}
+
+/// Scoped read/write access to the bytes behind a [`SecretBytes`].
+pub struct SecretBytesGuardMut<'a> {
+    buf: &'a mut [u8],
+}
+
+impl<'a> Deref for SecretBytesGuardMut<'a> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.buf
+    }
+}
+
+impl<'a> DerefMut for SecretBytesGuardMut<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.buf
+    }
+}
+
+/// Zeroes `buf` with a write the compiler cannot optimize away.
+fn zero(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+fn lock(buf: &mut [u8]) {
+    if buf.is_empty() {
+        return;
+    }
+    unsafe {
+        libc::mlock(buf.as_ptr() as *const libc::c_void, buf.len());
+        #[cfg(target_os = "linux")]
+        libc::madvise(buf.as_mut_ptr() as *mut libc::c_void, buf.len(), libc::MADV_DONTDUMP);
+    }
+}
+
+#[cfg(not(unix))]
+fn lock(_buf: &mut [u8]) {}
+
+#[cfg(unix)]
+fn unlock(buf: &mut [u8]) {
+    if buf.is_empty() {
+        return;
+    }
+    unsafe {
+        libc::munlock(buf.as_ptr() as *const libc::c_void, buf.len());
+    }
+}
+
+#[cfg(not(unix))]
+fn unlock(_buf: &mut [u8]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_slice_round_trips() {
+        let secret = SecretBytes::from_slice(&[1, 2, 3, 4]);
+        assert_eq!(secret.borrow().as_ref(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn clone_is_an_independent_copy() {
+        let mut secret = SecretBytes::from_slice(&[1, 2, 3, 4]);
+        let clone = secret.clone();
+        secret.borrow_mut()[0] = 0xff;
+        assert_eq!(clone.borrow().as_ref(), &[1, 2, 3, 4]);
+        assert_eq!(secret.borrow().as_ref(), &[0xff, 2, 3, 4]);
+    }
+
+    #[test]
+    fn debug_never_prints_the_bytes() {
+        let secret = SecretBytes::from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let rendered = format!("{:?}", secret);
+        assert!(!rendered.contains("222"));
+        assert!(rendered.contains("4 bytes"));
+    }
+
+    #[test]
+    fn zero_overwrites_every_byte() {
+        let mut buf = [0x11u8; 32];
+        zero(&mut buf);
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn drop_runs_without_error_on_empty_and_nonempty_buffers() {
+        drop(SecretBytes::new(0));
+        drop(SecretBytes::from_slice(&[0x11; 32]));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{PubkeyStruct, SeckeyStruct, SigStruct, PREHASH_BYTES, SIGALG_HASHED};
+use sodiumoxide::crypto::generichash::State;
+use std::io;
+use std::io::Read;
+
+/// Read chunk size used while streaming a file through the prehash digest.
+const PREHASHING_CHUNK_SIZE: usize = 65536;
+
+/// Streams `reader` through BLAKE2b-512 and returns the resulting digest.
+///
+/// Used for the `SIGALG_HASHED` signing mode so multi-gigabyte inputs never
+/// need to be held in memory at once.
+pub fn prehash<R: Read>(reader: &mut R) -> io::Result<[u8; PREHASH_BYTES]> {
+    let mut state = State::new(Some(PREHASH_BYTES), None)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to initialize BLAKE2b state"))?;
+    let mut chunk = [0u8; PREHASHING_CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        state
+            .update(&chunk[..n])
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "BLAKE2b update failed"))?;
+    }
+    let digest = state
+        .finalize()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "BLAKE2b finalize failed"))?;
+    let mut out = [0u8; PREHASH_BYTES];
+    out.copy_from_slice(digest.as_ref());
+    Ok(out)
+}
+
+/// Signs `reader` in prehashed mode: the content is streamed through
+/// BLAKE2b-512 and the signature is computed over the digest, not the raw
+/// content. Writes `SIGALG_HASHED` into `SigStruct::sig_alg` so verifiers
+/// know to rehash, and binds `trusted_comment` to the signature via the
+/// minisign global-signature scheme.
+pub fn sign_prehashed<R: Read>(sk: &SeckeyStruct, reader: &mut R, trusted_comment: &str) -> io::Result<SigStruct> {
+    let digest = prehash(reader)?;
+    let sig = crate::sign(SIGALG_HASHED, &sk.keynum_sk.sk.borrow(), &digest)?;
+    let mut signature = SigStruct {
+        sig_alg: SIGALG_HASHED.bytes().collect(),
+        keynum: sk.keynum_sk.keynum.clone(),
+        sig,
+        ..SigStruct::default()
+    };
+    signature.sign_trusted_comment(sk, trusted_comment)?;
+    Ok(signature)
+}
+
+/// Verifies `reader` against `signature`, rehashing the content with
+/// BLAKE2b-512 if `signature.sig_alg` is `SIGALG_HASHED`, or checking the
+/// raw content directly otherwise. The global signature over the trusted
+/// comment is checked first, so a tampered comment is rejected before the
+/// (potentially expensive) content check runs. That check is ed25519-only:
+/// a secp256k1 `pk` is rejected with an error and its content is never
+/// checked.
+pub fn verify<R: Read>(pk: &PubkeyStruct, reader: &mut R, signature: &SigStruct) -> io::Result<bool> {
+    signature.verify_global_sig(pk)?;
+
+    if signature.sig_alg == SIGALG_HASHED.as_bytes() {
+        let digest = prehash(reader)?;
+        return Ok(signature.verify(pk, &digest));
+    }
+    let mut message = Vec::new();
+    reader.read_to_end(&mut message)?;
+    Ok(signature.verify(pk, &message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gen_keystruct, gen_keystruct_for, SIGALG_SECP256K1};
+    use std::io::Cursor;
+
+    #[test]
+    fn sign_prehashed_round_trips() {
+        let (pk, sk) = gen_keystruct();
+        let message = b"stream this through blake2b".to_vec();
+        let signature = sign_prehashed(&sk, &mut Cursor::new(&message), "trusted comment: for the record").unwrap();
+        assert!(verify(&pk, &mut Cursor::new(&message), &signature).unwrap());
+    }
+
+    #[test]
+    fn sign_prehashed_rejects_tampered_content() {
+        let (pk, sk) = gen_keystruct();
+        let message = b"stream this through blake2b".to_vec();
+        let signature = sign_prehashed(&sk, &mut Cursor::new(&message), "trusted comment: for the record").unwrap();
+        let tampered = b"stream THIS through blake2b".to_vec();
+        assert!(!verify(&pk, &mut Cursor::new(&tampered), &signature).unwrap());
+    }
+
+    #[test]
+    fn sign_prehashed_rejects_tampered_trusted_comment() {
+        let (pk, sk) = gen_keystruct();
+        let message = b"stream this through blake2b".to_vec();
+        let mut signature = sign_prehashed(&sk, &mut Cursor::new(&message), "trusted comment: for the record").unwrap();
+        signature.trusted_comment = "trusted comment: rewritten by an attacker".to_string();
+        assert!(verify(&pk, &mut Cursor::new(&message), &signature).is_err());
+    }
+
+    #[test]
+    fn sign_prehashed_rejects_secp256k1_keys_instead_of_panicking() {
+        let (_, sk) = gen_keystruct_for(SIGALG_SECP256K1);
+        let message = b"stream this through blake2b".to_vec();
+        assert!(sign_prehashed(&sk, &mut Cursor::new(&message), "trusted comment: for the record").is_err());
+    }
+}